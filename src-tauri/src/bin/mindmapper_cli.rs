@@ -0,0 +1,143 @@
+//! Tiny CLI that submits a prompt to an already-running MindMapper app over
+//! its local IPC socket and streams the result to the terminal, so mind-map
+//! generation can be scripted (editor plugins, shell pipelines) without
+//! driving the desktop window.
+//!
+//! Usage: mindmapper_cli --output-dir DIR [--model MODEL] [--hands-off] PROMPT
+
+use std::io::{BufRead, BufReader, Write};
+
+use interprocess::local_socket::{GenericFilePath, GenericNamespaced, Stream, ToFsName, ToNsName};
+
+/// Must match `SOCKET_NAME` in the app's `ipc` module.
+const SOCKET_NAME: &str = "mindmapper-ipc.sock";
+
+/// Must match `TOKEN_FILE_NAME` in the app's `ipc` module.
+const TOKEN_FILE_NAME: &str = "mindmapper-ipc.token";
+
+struct Args {
+    prompt: String,
+    output_dir: String,
+    model: Option<String>,
+    hands_off: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut output_dir = None;
+    let mut model = None;
+    let mut hands_off = false;
+    let mut prompt_parts = Vec::new();
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--output-dir" => output_dir = Some(iter.next().ok_or("--output-dir needs a value")?),
+            "--model" => model = iter.next(),
+            "--hands-off" => hands_off = true,
+            other => prompt_parts.push(other.to_string()),
+        }
+    }
+
+    Ok(Args {
+        prompt: prompt_parts.join(" "),
+        output_dir: output_dir.ok_or("--output-dir is required")?,
+        model,
+        hands_off,
+    })
+}
+
+fn connect() -> std::io::Result<Stream> {
+    match SOCKET_NAME.to_ns_name::<GenericNamespaced>() {
+        Ok(name) => Stream::connect(name),
+        Err(_) => Stream::connect(std::env::temp_dir().join(SOCKET_NAME).to_fs_name::<GenericFilePath>()?),
+    }
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("usage: mindmapper_cli --output-dir DIR [--model MODEL] [--hands-off] PROMPT");
+            std::process::exit(2);
+        }
+    };
+
+    let token = match std::fs::read_to_string(std::env::temp_dir().join(TOKEN_FILE_NAME)) {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("error: couldn't read IPC handshake token (is the app running?): {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut conn = match connect() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("error: couldn't connect to MindMapper (is the app running?): {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let request = serde_json::json!({
+        "cmd": "spawn",
+        "token": token,
+        "prompt": args.prompt,
+        "output_dir": args.output_dir,
+        "model": args.model,
+        "hands_off": args.hands_off,
+    });
+
+    if let Err(e) = writeln!(conn, "{}", request) {
+        eprintln!("error: failed to send request: {}", e);
+        std::process::exit(1);
+    }
+
+    let reader = BufReader::new(conn);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("error: connection read failed: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+
+        if let Some(err) = value.get("error").and_then(|v| v.as_str()) {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+
+        if let Some(session_id) = value.get("sessionId").and_then(|v| v.as_str()) {
+            eprintln!("session: {}", session_id);
+            continue;
+        }
+
+        let Some(event) = value.get("event").and_then(|v| v.as_str()) else { continue };
+        let data = value.get("data").cloned().unwrap_or(serde_json::Value::Null);
+
+        match event {
+            "claude:token" => {
+                if let Some(text) = data.get("text").and_then(|v| v.as_str()) {
+                    print!("{}", text);
+                    let _ = std::io::stdout().flush();
+                }
+            }
+            "claude:error" => {
+                if let Some(message) = data.get("message").and_then(|v| v.as_str()) {
+                    eprintln!("[stderr] {}", message);
+                }
+            }
+            "claude:complete" | "claude:cancelled" => {
+                println!();
+                println!("{}: {}", event, data);
+                break;
+            }
+            _ => {}
+        }
+    }
+}
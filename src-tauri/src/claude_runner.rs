@@ -0,0 +1,229 @@
+//! Shared "spawn `claude` and stream its output" logic.
+//!
+//! Pulled out of the `spawn_claude` Tauri command so the local IPC server
+//! (`ipc` module) can submit sessions the same way the frontend does,
+//! sharing one `SessionManager` and the same `claude:*` events rather than
+//! re-implementing spawning for IPC-submitted jobs.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::console_log;
+use crate::session::{SessionManager, SessionStatus};
+use crate::stream_json::{self, ClaudeEvent, LineReader};
+
+/// Spawn `claude` with `prompt` and stream its output via the same
+/// `claude:*` events documented on the `spawn_claude` command, regardless
+/// of whether the caller was the frontend or the local IPC server. Returns
+/// the generated session id immediately; the process itself keeps running
+/// in background threads.
+pub fn spawn(
+    app: &AppHandle,
+    prompt: String,
+    output_dir: String,
+    model: Option<String>,
+    hands_off: Option<bool>,
+) -> Result<String, String> {
+    let mut args: Vec<String> = vec![
+        "-p".to_string(),
+        prompt.clone(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+    ];
+
+    if let Some(ref m) = model {
+        args.push("--model".to_string());
+        args.push(m.clone());
+    }
+
+    if hands_off.unwrap_or(false) {
+        args.push("--allowedTools".to_string());
+        args.push("Bash,Read,Write,Edit,MultiEdit,Glob,Grep,LS,TodoRead,TodoWrite".to_string());
+    }
+
+    log::info!(
+        "Spawning Claude Code — prompt: {} chars, dir: {}, model: {:?}",
+        prompt.len(),
+        output_dir,
+        model
+    );
+
+    let mut command = Command::new("claude");
+    command
+        .args(&args)
+        .current_dir(&output_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    #[cfg(not(target_os = "windows"))]
+    crate::process_control::new_process_group(&mut command);
+
+    let mut child = command.spawn().map_err(|e| format!("Failed to spawn Claude CLI: {}", e))?;
+    let pid = child.id();
+
+    let session_id = app.state::<SessionManager>().register(pid, model.clone(), output_dir.clone());
+
+    let _ = app.emit("claude:started", serde_json::json!({
+        "sessionId": session_id,
+        "pid": pid,
+    }));
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let app_stdout = app.clone();
+    let app_stderr = app.clone();
+    let session_stdout = session_id.clone();
+    let session_stderr = session_id.clone();
+
+    let stdout_handle = std::thread::spawn(move || console_log::with_session(&session_stdout, || {
+        if let Some(out) = stdout {
+            let sessions = app_stdout.state::<SessionManager>();
+            let mut reader = LineReader::new(out);
+
+            loop {
+                let text = match reader.next_line() {
+                    Ok(Some(text)) => text,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("stdout read error: {}", e);
+                        break;
+                    }
+                };
+
+                if text.is_empty() {
+                    continue;
+                }
+
+                match stream_json::parse_line(&text) {
+                    Some(events) => {
+                        for event in events {
+                            if let ClaudeEvent::UsageDelta { .. } | ClaudeEvent::Result { .. } = &event {
+                                if let Some(totals) = sessions.apply_usage(&session_stdout, &event) {
+                                    let _ = app_stdout.emit("claude:usage", serde_json::json!({
+                                        "sessionId": session_stdout,
+                                        "totals": totals,
+                                    }));
+                                }
+                            }
+
+                            match event {
+                                ClaudeEvent::AssistantText { text } => {
+                                    let _ = app_stdout.emit("claude:token", serde_json::json!({
+                                        "sessionId": session_stdout,
+                                        "text": text,
+                                    }));
+                                }
+                                ClaudeEvent::ToolUse { name, input } => {
+                                    let _ = app_stdout.emit("claude:tool", serde_json::json!({
+                                        "sessionId": session_stdout,
+                                        "phase": "use",
+                                        "name": name,
+                                        "input": input,
+                                    }));
+                                }
+                                ClaudeEvent::ToolResult { content, is_error } => {
+                                    let _ = app_stdout.emit("claude:tool", serde_json::json!({
+                                        "sessionId": session_stdout,
+                                        "phase": "result",
+                                        "content": content,
+                                        "isError": is_error,
+                                    }));
+                                }
+                                // UsageDelta/Result are already reported via
+                                // "claude:usage" above; emitting them here too
+                                // would just be a redundant debug dump.
+                                ClaudeEvent::UsageDelta { .. } | ClaudeEvent::Result { .. } => {}
+                                ClaudeEvent::SystemInit { session_id, model } => {
+                                    let _ = app_stdout.emit("claude:progress", serde_json::json!({
+                                        "sessionId": session_stdout,
+                                        "type": "system_init",
+                                        "data": { "sessionId": session_id, "model": model },
+                                    }));
+                                }
+                                ClaudeEvent::Unknown(value) => {
+                                    let _ = app_stdout.emit("claude:progress", serde_json::json!({
+                                        "sessionId": session_stdout,
+                                        "type": "unknown",
+                                        "data": value,
+                                    }));
+                                }
+                            }
+                        }
+                    }
+                    None => {
+                        let _ = app_stdout.emit("claude:progress", serde_json::json!({
+                            "sessionId": session_stdout,
+                            "type": "text",
+                            "data": text,
+                        }));
+                    }
+                }
+            }
+        }
+    }));
+
+    let stderr_handle = std::thread::spawn(move || console_log::with_session(&session_stderr, || {
+        if let Some(err) = stderr {
+            let reader = BufReader::new(err);
+            for line in reader.lines() {
+                match line {
+                    Ok(text) => {
+                        let _ = app_stderr.emit("claude:error", serde_json::json!({
+                            "sessionId": session_stderr,
+                            "message": text,
+                        }));
+                    }
+                    Err(e) => {
+                        log::error!("stderr read error: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }));
+
+    let app_complete = app.clone();
+    let session_complete = session_id.clone();
+
+    std::thread::spawn(move || console_log::with_session(&session_complete, || {
+        let _ = stdout_handle.join();
+        let _ = stderr_handle.join();
+
+        let exit_code = match child.wait() {
+            Ok(status) => status.code().unwrap_or(-1),
+            Err(e) => {
+                log::error!("Failed to wait for Claude process: {}", e);
+                -1
+            }
+        };
+
+        let sessions = app_complete.state::<SessionManager>();
+
+        if sessions.status_of(&session_complete) == Some(SessionStatus::Cancelled) {
+            let escalated = sessions.escalated_of(&session_complete).unwrap_or(false);
+            let _ = app_complete.emit("claude:cancelled", serde_json::json!({
+                "sessionId": session_complete,
+                "exitCode": exit_code,
+                "escalated": escalated,
+            }));
+            log::info!("Claude Code session {} cancelled (escalated: {})", session_complete, escalated);
+        } else {
+            sessions.finish(
+                &session_complete,
+                if exit_code == 0 { SessionStatus::Completed } else { SessionStatus::Failed },
+            );
+
+            let _ = app_complete.emit("claude:complete", serde_json::json!({
+                "sessionId": session_complete,
+                "exitCode": exit_code,
+                "success": exit_code == 0,
+            }));
+
+            log::info!("Claude Code session {} completed with exit code {}", session_complete, exit_code);
+        }
+    }));
+
+    Ok(session_id)
+}
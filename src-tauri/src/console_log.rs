@@ -0,0 +1,116 @@
+//! Forwards backend `log` records to the frontend so the desktop UI has an
+//! always-available diagnostics console instead of opaque failures.
+//!
+//! `tauri_plugin_log` only writes to stdout/file in debug builds, so a
+//! release build (or a headless IPC session) has no way to see what's going
+//! on. `ConsoleLogger` is a second `log::Log` sink that formats each record
+//! and emits it as a `backend:log` event, while also keeping the last
+//! `CAPACITY` lines in a ring buffer so a newly opened console pane can
+//! back-fill history via `get_log_tail`.
+
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+const CAPACITY: usize = 500;
+
+static LOGGER: OnceLock<&'static ConsoleLogger> = OnceLock::new();
+
+/// One formatted log line, as shown in the console pane.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsoleEvent {
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Unix timestamp in milliseconds.
+    pub timestamp: u128,
+    /// Present when the record was logged from inside a `spawn_claude`
+    /// worker thread, so the console pane can filter by session.
+    pub session_id: Option<String>,
+}
+
+/// Ring buffer of recent console lines plus the `AppHandle` used to emit
+/// new ones as they arrive.
+pub struct ConsoleLogger {
+    app: AppHandle,
+    tail: Mutex<std::collections::VecDeque<ConsoleEvent>>,
+}
+
+impl ConsoleLogger {
+    pub fn init(app: AppHandle) {
+        let logger = Box::leak(Box::new(ConsoleLogger {
+            app,
+            tail: Mutex::new(std::collections::VecDeque::with_capacity(CAPACITY)),
+        }));
+        log::set_logger(logger).expect("console logger already set");
+        log::set_max_level(log::LevelFilter::Info);
+        let _ = LOGGER.set(logger);
+    }
+
+    /// Snapshot of the last `CAPACITY` log lines, for `get_log_tail`.
+    pub fn tail(&self) -> Vec<ConsoleEvent> {
+        self.tail.lock().expect("console log tail poisoned").iter().cloned().collect()
+    }
+}
+
+/// Snapshot of recent log lines, or empty if the logger hasn't been
+/// initialized yet (should only happen before `run()`'s `setup` runs).
+pub fn tail() -> Vec<ConsoleEvent> {
+    LOGGER.get().map(|logger| logger.tail()).unwrap_or_default()
+}
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // Also print to stdout, so `tauri_plugin_log`'s old behavior
+        // (visible output when running `tauri dev`) keeps working.
+        println!("[{}] {}: {}", record.level(), record.target(), record.args());
+
+        let event = ConsoleEvent {
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0),
+            session_id: CURRENT_SESSION.with(|s| s.borrow().clone()),
+        };
+
+        {
+            let mut tail = self.tail.lock().expect("console log tail poisoned");
+            if tail.len() == CAPACITY {
+                tail.pop_front();
+            }
+            tail.push_back(event.clone());
+        }
+
+        let _ = self.app.emit("backend:log", event);
+    }
+
+    fn flush(&self) {}
+}
+
+thread_local! {
+    /// The session id to attach to log records made from the current
+    /// thread, set by `spawn_claude`'s worker threads via `with_session`.
+    static CURRENT_SESSION: std::cell::RefCell<Option<String>> = std::cell::RefCell::new(None);
+}
+
+/// Run `f` with `session_id` attached to any log record emitted from this
+/// thread for the duration of the call.
+pub fn with_session<R>(session_id: &str, f: impl FnOnce() -> R) -> R {
+    CURRENT_SESSION.with(|s| *s.borrow_mut() = Some(session_id.to_string()));
+    let result = f();
+    CURRENT_SESSION.with(|s| *s.borrow_mut() = None);
+    result
+}
@@ -0,0 +1,276 @@
+//! Local IPC listener so external tools (editor plugins, shell pipelines,
+//! the `mindmapper_cli` binary) can queue mind-map jobs against an
+//! already-running app instead of only driving it through the window.
+//!
+//! Protocol is line-delimited JSON over a platform-local socket (a Unix
+//! domain socket on *nix, a named pipe on Windows — `interprocess` gives us
+//! one API for both). On Linux the socket lives in the abstract namespace,
+//! which isn't gated by filesystem permissions, so every request must carry
+//! the handshake `token` this module writes to a 0600 file at startup —
+//! anything without it is rejected before a `claude` process is ever
+//! spawned. A client sends one request line:
+//!
+//!   {"cmd":"spawn","token":"...","prompt":"...","output_dir":"...","model":null,"hands_off":false}
+//!
+//! and gets back a line `{"sessionId":"..."}`, followed by one JSON line
+//! per `claude:*` event for that session (the same events the frontend
+//! gets, except `claude:started`, which the `sessionId` line stands in
+//! for), until a terminal `claude:complete`/`claude:cancelled` line closes
+//! the connection. Sessions submitted this way share the same
+//! `SessionManager` as UI-submitted ones, so `list_sessions`/
+//! `cancel_claude` work on them too.
+
+use std::io::{BufRead, BufReader, Write};
+use std::sync::OnceLock;
+
+use interprocess::local_socket::{
+    GenericFilePath, GenericNamespaced, ListenerOptions, Stream, ToFsName, ToNsName,
+};
+use rand::RngCore;
+use serde::Deserialize;
+use tauri::{AppHandle, Listener};
+
+/// Name of the socket/pipe. Namespaced on platforms that support it
+/// (Windows, Linux via the abstract namespace); falls back to a path under
+/// the OS temp dir everywhere else.
+const SOCKET_NAME: &str = "mindmapper-ipc.sock";
+
+/// Where the handshake token is written, readable only by the user who
+/// started the app (`mindmapper_cli` reads it from the same path).
+const TOKEN_FILE_NAME: &str = "mindmapper-ipc.token";
+
+/// Token every request must echo back in its `token` field. Generated fresh
+/// each time `start` runs, so a client has to have read the current token
+/// file — it can't replay one from a previous run.
+static EXPECTED_TOKEN: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    token: String,
+    #[serde(flatten)]
+    request: Request,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Spawn {
+        prompt: String,
+        output_dir: String,
+        model: Option<String>,
+        hands_off: Option<bool>,
+    },
+}
+
+fn token_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(TOKEN_FILE_NAME)
+}
+
+/// Generate a fresh handshake token, write it to a 0600 file next to the
+/// socket, and remember it for `handle_connection` to check requests
+/// against.
+fn write_token_file() -> std::io::Result<()> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let path = token_path();
+    std::fs::write(&path, &token)?;
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    let _ = EXPECTED_TOKEN.set(token);
+    Ok(())
+}
+
+/// Start the IPC listener on a background thread. Errors (e.g. a stale
+/// socket file from a previous crash) are logged rather than propagated,
+/// since IPC is optional — the app should still run without it.
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+        if let Err(e) = write_token_file() {
+            log::error!("IPC: failed to write handshake token file: {}", e);
+            return;
+        }
+
+        let name = match SOCKET_NAME.to_ns_name::<GenericNamespaced>() {
+            Ok(name) => name,
+            Err(_) => match std::env::temp_dir().join(SOCKET_NAME).to_fs_name::<GenericFilePath>() {
+                Ok(name) => name,
+                Err(e) => {
+                    log::error!("IPC: couldn't build socket name: {}", e);
+                    return;
+                }
+            },
+        };
+
+        let listener = match ListenerOptions::new().name(name).create_sync() {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("IPC: failed to start local socket listener: {}", e);
+                return;
+            }
+        };
+
+        log::info!("IPC listener ready on {}", SOCKET_NAME);
+
+        for conn in listener.incoming() {
+            let conn = match conn {
+                Ok(conn) => conn,
+                Err(e) => {
+                    log::warn!("IPC: connection accept error: {}", e);
+                    continue;
+                }
+            };
+
+            let app = app.clone();
+            std::thread::spawn(move || handle_connection(app, conn));
+        }
+    });
+}
+
+/// How long to wait for the next `claude:*` event before giving up on a
+/// connection, so a job whose session somehow never reports back (or whose
+/// events get lost) can't block the handling thread forever.
+const EVENT_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+const EVENTS: &[&str] = &[
+    "claude:token",
+    "claude:tool",
+    "claude:usage",
+    "claude:progress",
+    "claude:error",
+    "claude:complete",
+    "claude:cancelled",
+];
+
+/// How long a client gets to send its request line before the connection's
+/// handling thread gives up on it, so one that connects and never writes
+/// anything can't hold that thread forever. The helper thread reading the
+/// line stays blocked until the client sends something or disconnects.
+const HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+fn handle_connection(app: AppHandle, conn: Stream) {
+    let mut writer = match conn.try_clone() {
+        Ok(w) => w,
+        Err(e) => {
+            log::warn!("IPC: failed to clone connection: {}", e);
+            return;
+        }
+    };
+
+    // `read_line` has no built-in timeout, so run it on its own thread and
+    // bound how long we wait for it here — a client that connects and never
+    // sends a line would otherwise block this thread forever.
+    let (line_tx, line_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(conn);
+        let mut line = String::new();
+        let result = reader.read_line(&mut line).map(|n| if n == 0 { None } else { Some(line) });
+        let _ = line_tx.send(result);
+    });
+
+    let line = match line_rx.recv_timeout(HANDSHAKE_TIMEOUT) {
+        Ok(Ok(Some(line))) => line,
+        Ok(Ok(None)) => return,
+        Ok(Err(e)) => {
+            log::warn!("IPC: connection read error: {}", e);
+            return;
+        }
+        Err(_) => {
+            log::warn!("IPC: client sent no request within {:?}, closing connection", HANDSHAKE_TIMEOUT);
+            let _ = writeln!(writer, "{}", serde_json::json!({"error": "timed out waiting for request"}));
+            return;
+        }
+    };
+
+    let envelope: Envelope = match serde_json::from_str(line.trim_end()) {
+        Ok(envelope) => envelope,
+        Err(e) => {
+            let _ = writeln!(writer, "{}", serde_json::json!({"error": e.to_string()}));
+            return;
+        }
+    };
+
+    if Some(&envelope.token) != EXPECTED_TOKEN.get() {
+        log::warn!("IPC: rejected connection with invalid handshake token");
+        let _ = writeln!(writer, "{}", serde_json::json!({"error": "invalid or missing token"}));
+        return;
+    }
+
+    let Request::Spawn { prompt, output_dir, model, hands_off } = envelope.request;
+
+    // Register listeners *before* spawning, since the worker threads
+    // `claude_runner::spawn` starts can emit (and a fast-failing job can
+    // even complete) before we'd otherwise get around to listening. Every
+    // event for every session is buffered here and filtered by session id
+    // below, since the session id itself isn't known until `spawn` returns.
+    let (tx, rx) = std::sync::mpsc::channel::<(String, serde_json::Value)>();
+    let mut handler_ids = Vec::with_capacity(EVENTS.len());
+    for &name in EVENTS {
+        let tx = tx.clone();
+        let id = app.listen(name, move |event| {
+            if let Ok(payload) = serde_json::from_str::<serde_json::Value>(event.payload()) {
+                let _ = tx.send((name.to_string(), payload));
+            }
+        });
+        handler_ids.push(id);
+    }
+
+    let session_id = match crate::claude_runner::spawn(&app, prompt, output_dir, model, hands_off) {
+        Ok(id) => id,
+        Err(e) => {
+            for id in handler_ids {
+                app.unlisten(id);
+            }
+            let _ = writeln!(writer, "{}", serde_json::json!({"error": e}));
+            return;
+        }
+    };
+
+    if writeln!(writer, "{}", serde_json::json!({"sessionId": session_id})).is_err() {
+        for id in handler_ids {
+            app.unlisten(id);
+        }
+        return;
+    }
+
+    stream_events_for(&session_id, rx, writer);
+
+    for id in handler_ids {
+        app.unlisten(id);
+    }
+}
+
+/// Forward every buffered `claude:*` event belonging to `session_id` to
+/// `writer` as a JSON line, until a terminal event closes the stream or no
+/// event arrives within `EVENT_IDLE_TIMEOUT`.
+fn stream_events_for(
+    session_id: &str,
+    rx: std::sync::mpsc::Receiver<(String, serde_json::Value)>,
+    mut writer: impl Write,
+) {
+    loop {
+        let (event, payload) = match rx.recv_timeout(EVENT_IDLE_TIMEOUT) {
+            Ok(msg) => msg,
+            Err(_) => {
+                log::warn!("IPC: session {} produced no events for {:?}, closing connection", session_id, EVENT_IDLE_TIMEOUT);
+                let _ = writeln!(writer, "{}", serde_json::json!({"error": "timed out waiting for session events"}));
+                return;
+            }
+        };
+
+        if payload.get("sessionId").and_then(|v| v.as_str()) != Some(session_id) {
+            continue;
+        }
+
+        let is_terminal = event == "claude:complete" || event == "claude:cancelled";
+        if writeln!(writer, "{}", serde_json::json!({"event": event, "data": payload})).is_err() || is_terminal {
+            return;
+        }
+    }
+}
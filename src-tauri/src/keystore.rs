@@ -0,0 +1,138 @@
+//! Encryption-at-rest for provider API keys.
+//!
+//! `set_api_key`/`get_api_key` used to write the raw key straight into
+//! `mindmapper-settings.json` via `tauri_plugin_store`, which lands on disk
+//! in cleartext. Instead we derive a key from a user-supplied passphrase
+//! with Argon2id, encrypt each provider's key with XChaCha20-Poly1305 using
+//! a fresh random nonce, and store `{salt, nonce, ciphertext}`. The
+//! passphrase-derived key only ever lives in memory, behind
+//! `unlock_keystore`/`lock_keystore`.
+
+use std::sync::Mutex;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+const SALT_LEN: usize = 16;
+
+/// What actually gets written to the settings store for a given provider.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SealedKey {
+    /// Base64-encoded Argon2id salt used to derive the key this was sealed
+    /// with, so a blob is self-describing even if the in-memory keystore
+    /// was cleared and re-derived from the same passphrase.
+    salt: String,
+    /// Base64-encoded XChaCha20-Poly1305 nonce.
+    nonce: String,
+    /// Base64-encoded ciphertext (includes the AEAD tag).
+    ciphertext: String,
+}
+
+struct Unlocked {
+    key: [u8; 32],
+    salt: [u8; SALT_LEN],
+}
+
+/// Holds the passphrase-derived key (and the salt it was derived with) in
+/// memory while the keystore is unlocked. Cleared entirely on
+/// `lock_keystore`.
+#[derive(Default)]
+pub struct Keystore(Mutex<Option<Unlocked>>);
+
+impl Keystore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.0.lock().expect("keystore poisoned").is_some()
+    }
+
+    /// Derive the key from `passphrase` and `salt` and hold it in memory.
+    /// `salt` should be a freshly generated salt the first time a keystore
+    /// is created, or the salt recovered from an existing sealed key on
+    /// subsequent unlocks.
+    pub fn unlock(&self, passphrase: &str, salt: [u8; SALT_LEN]) -> Result<(), String> {
+        let key = derive_key(passphrase, &salt)?;
+        *self.0.lock().expect("keystore poisoned") = Some(Unlocked { key, salt });
+        Ok(())
+    }
+
+    pub fn lock(&self) {
+        *self.0.lock().expect("keystore poisoned") = None;
+    }
+
+    /// Generate a fresh random salt, e.g. for the first `unlock_keystore`
+    /// call before any secret has been sealed yet.
+    pub fn random_salt() -> [u8; SALT_LEN] {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        salt
+    }
+
+    /// Encrypt `plaintext` under the key currently held in memory, with a
+    /// fresh nonce. Fails if the keystore hasn't been unlocked.
+    pub fn seal(&self, plaintext: &str) -> Result<SealedKey, String> {
+        let guard = self.0.lock().expect("keystore poisoned");
+        let unlocked = guard.as_ref().ok_or("keystore is locked")?;
+
+        let cipher = XChaCha20Poly1305::new((&unlocked.key).into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| "encryption failed".to_string())?;
+
+        Ok(SealedKey {
+            salt: base64_encode(&unlocked.salt),
+            nonce: base64_encode(&nonce),
+            ciphertext: base64_encode(&ciphertext),
+        })
+    }
+
+    /// Decrypt a value previously produced by `seal`, using the key held in
+    /// memory from `unlock_keystore`. Returns `Err` (rather than panicking)
+    /// when locked, so `get_api_key` can fail cleanly.
+    pub fn open(&self, sealed: &SealedKey) -> Result<String, String> {
+        let guard = self.0.lock().expect("keystore poisoned");
+        let unlocked = guard.as_ref().ok_or("keystore is locked")?;
+
+        let nonce_bytes = base64_decode(&sealed.nonce)?;
+        let ciphertext = base64_decode(&sealed.ciphertext)?;
+
+        let cipher = XChaCha20Poly1305::new((&unlocked.key).into());
+        let nonce = XNonce::from_slice(&nonce_bytes);
+        let plaintext = cipher
+            .decrypt(nonce, ciphertext.as_slice())
+            .map_err(|_| "decryption failed".to_string())?;
+
+        String::from_utf8(plaintext).map_err(|e| e.to_string())
+    }
+
+    /// Recover the salt a `SealedKey` was derived with, so a reopened app
+    /// can re-derive the same key from the passphrase on `unlock_keystore`.
+    pub fn salt_of(sealed: &SealedKey) -> Result<[u8; SALT_LEN], String> {
+        let bytes = base64_decode(&sealed.salt)?;
+        bytes.try_into().map_err(|_| "malformed salt".to_string())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8; SALT_LEN]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.decode(s).map_err(|e| e.to_string())
+}
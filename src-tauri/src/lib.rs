@@ -1,13 +1,19 @@
 // MindMapper — Tauri backend commands for Claude Code integration
 
-use std::sync::Mutex;
+mod claude_runner;
+mod console_log;
+mod ipc;
+mod keystore;
+mod process_control;
+mod session;
+mod stream_json;
+
 use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_store::StoreExt;
 
-/// Holds an optional child process ID for cancellation.
-struct ClaudeProcess(Mutex<Option<u32>>);
+use keystore::{Keystore, SealedKey};
+use session::{SessionManager, SessionStatus};
 
 /// Detect if `claude` CLI is installed and return its version.
 #[tauri::command]
@@ -45,225 +51,156 @@ async fn detect_claude_cli() -> Result<serde_json::Value, String> {
 /// Spawn Claude Code with a prompt and stream output via Tauri events.
 ///
 /// Events emitted to the frontend:
-///   - "claude:progress" — each line of stdout (parsed JSON or raw text)
+///   - "claude:token"    — assistant text as it streams in
+///   - "claude:tool"     — a tool use or tool result, with `phase: "use"|"result"`
+///   - "claude:usage"    — running token/cost totals for the session
+///   - "claude:progress" — anything else decoded from stdout, or raw text
+///     that didn't parse as a stream-json event
 ///   - "claude:error"    — each line of stderr
-///   - "claude:complete" — when the process exits, includes exit code
+///   - "claude:complete" — when the process exits normally, includes exit code
+///   - "claude:cancelled" — when the process exits after `cancel_claude`,
+///     instead of "claude:complete"; includes whether SIGKILL was needed
 #[tauri::command]
 async fn spawn_claude(
     app: AppHandle,
-    state: State<'_, ClaudeProcess>,
     prompt: String,
     output_dir: String,
     model: Option<String>,
     hands_off: Option<bool>,
 ) -> Result<serde_json::Value, String> {
-    // Build the claude command arguments
-    let mut args: Vec<String> = vec![
-        "-p".to_string(),
-        prompt.clone(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-    ];
-
-    // Add model override if specified
-    if let Some(ref m) = model {
-        args.push("--model".to_string());
-        args.push(m.clone());
-    }
-
-    // Add allowed tools for hands-off mode
-    if hands_off.unwrap_or(false) {
-        args.push("--allowedTools".to_string());
-        args.push("Bash,Read,Write,Edit,MultiEdit,Glob,Grep,LS,TodoRead,TodoWrite".to_string());
-    }
-
-    log::info!(
-        "Spawning Claude Code — prompt: {} chars, dir: {}, model: {:?}",
-        prompt.len(),
-        output_dir,
-        model
-    );
-
-    // Spawn the child process
-    let mut child = Command::new("claude")
-        .args(&args)
-        .current_dir(&output_dir)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to spawn Claude CLI: {}", e))?;
-
-    let pid = child.id();
-
-    // Store the PID for cancellation
-    {
-        let mut proc = state.0.lock().map_err(|e| e.to_string())?;
-        *proc = Some(pid);
-    }
-
-    let session_id = format!("session_{}", pid);
-
-    // Emit session started event
-    let _ = app.emit("claude:started", serde_json::json!({
-        "sessionId": session_id,
-        "pid": pid,
-    }));
-
-    // Take ownership of stdout and stderr
-    let stdout = child.stdout.take();
-    let stderr = child.stderr.take();
-    let app_stdout = app.clone();
-    let app_stderr = app.clone();
-    let session_stdout = session_id.clone();
-    let session_stderr = session_id.clone();
-
-    // Stream stdout in a background thread
-    let stdout_handle = std::thread::spawn(move || {
-        if let Some(out) = stdout {
-            let reader = BufReader::new(out);
-            for line in reader.lines() {
-                match line {
-                    Ok(text) => {
-                        // Try to parse as JSON, fall back to raw text
-                        let payload = match serde_json::from_str::<serde_json::Value>(&text) {
-                            Ok(json) => serde_json::json!({
-                                "sessionId": session_stdout,
-                                "type": "json",
-                                "data": json,
-                            }),
-                            Err(_) => serde_json::json!({
-                                "sessionId": session_stdout,
-                                "type": "text",
-                                "data": text,
-                            }),
-                        };
-                        let _ = app_stdout.emit("claude:progress", payload);
-                    }
-                    Err(e) => {
-                        log::error!("stdout read error: {}", e);
-                        break;
-                    }
-                }
-            }
-        }
-    });
-
-    // Stream stderr in a background thread
-    let stderr_handle = std::thread::spawn(move || {
-        if let Some(err) = stderr {
-            let reader = BufReader::new(err);
-            for line in reader.lines() {
-                match line {
-                    Ok(text) => {
-                        let _ = app_stderr.emit("claude:error", serde_json::json!({
-                            "sessionId": session_stderr,
-                            "message": text,
-                        }));
-                    }
-                    Err(e) => {
-                        log::error!("stderr read error: {}", e);
-                        break;
-                    }
-                }
-            }
-        }
-    });
-
-    // Wait for completion in a background thread
-    let app_complete = app.clone();
-    let session_complete = session_id.clone();
-    let state_clone = std::sync::Arc::new(Mutex::new(()));
-
-    std::thread::spawn(move || {
-        // Wait for stdout/stderr threads to finish
-        let _ = stdout_handle.join();
-        let _ = stderr_handle.join();
-
-        // Wait for the child process
-        let exit_code = match child.wait() {
-            Ok(status) => status.code().unwrap_or(-1),
-            Err(e) => {
-                log::error!("Failed to wait for Claude process: {}", e);
-                -1
-            }
-        };
-
-        let _ = app_complete.emit("claude:complete", serde_json::json!({
-            "sessionId": session_complete,
-            "exitCode": exit_code,
-            "success": exit_code == 0,
-        }));
-
-        log::info!("Claude Code session {} completed with exit code {}", session_complete, exit_code);
-        drop(state_clone);
-    });
+    let session_id = claude_runner::spawn(&app, prompt, output_dir, model, hands_off)?;
 
     Ok(serde_json::json!({
         "sessionId": session_id,
-        "pid": pid,
         "status": "started"
     }))
 }
 
-/// Cancel a running Claude Code subprocess.
-#[tauri::command]
-async fn cancel_claude(state: State<'_, ClaudeProcess>) -> Result<serde_json::Value, String> {
-    let mut proc = state.0.lock().map_err(|e| e.to_string())?;
+/// How long to wait after SIGTERM before escalating to SIGKILL, unless the
+/// caller specifies a different grace period.
+const DEFAULT_CANCEL_GRACE_MS: u64 = 3000;
 
-    if let Some(pid) = proc.take() {
-        log::info!("Cancelling Claude Code process (PID: {})", pid);
+/// Cancel a running Claude Code subprocess by its session id. On Unix this
+/// signals the whole process group (Claude plus any Bash/tool subprocesses
+/// it spawned), escalating from SIGTERM to SIGKILL if it hasn't exited
+/// within `grace_period_ms`. The `claude:cancelled` event (emitted once the
+/// process actually exits) reports whether escalation was needed.
+#[tauri::command]
+async fn cancel_claude(
+    app: AppHandle,
+    state: State<'_, SessionManager>,
+    session_id: String,
+    grace_period_ms: Option<u64>,
+) -> Result<serde_json::Value, String> {
+    if let Some(pid) = state.pid_of(&session_id) {
+        log::info!("Cancelling Claude Code session {} (PID: {})", session_id, pid);
+        state.finish(&session_id, SessionStatus::Cancelled);
 
-        // On Windows, use taskkill to terminate the process tree
+        // On Windows, taskkill /T /F already force-kills the whole tree.
         #[cfg(target_os = "windows")]
         {
             let _ = Command::new("taskkill")
                 .args(["/PID", &pid.to_string(), "/T", "/F"])
                 .output();
+            state.set_escalated(&session_id, true);
         }
 
-        // On Unix, send SIGTERM
+        // On Unix, send SIGTERM to the process group and escalate to
+        // SIGKILL in the background if it doesn't exit within the grace
+        // period. The actual exit is still observed by spawn_claude's wait
+        // thread, which is what emits "claude:cancelled".
         #[cfg(not(target_os = "windows"))]
         {
-            unsafe {
-                libc::kill(pid as i32, libc::SIGTERM);
-            }
+            process_control::terminate_group(pid);
+
+            let grace = grace_period_ms.unwrap_or(DEFAULT_CANCEL_GRACE_MS);
+            let session_id = session_id.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(grace));
+                // Record escalation *before* sending SIGKILL: the wait
+                // thread's `child.wait()` can return the moment the signal
+                // lands, and it reads `escalated_of()` to build the
+                // "claude:cancelled" payload — setting the flag after the
+                // kill left a window where that read could still see the
+                // pre-escalation `false`.
+                if process_control::process_alive(pid) {
+                    log::warn!("Session {} did not exit within {}ms, sending SIGKILL", session_id, grace);
+                    app.state::<SessionManager>().set_escalated(&session_id, true);
+                    process_control::kill_group(pid);
+                } else {
+                    app.state::<SessionManager>().set_escalated(&session_id, false);
+                }
+            });
         }
 
         Ok(serde_json::json!({
             "cancelled": true,
+            "sessionId": session_id,
             "pid": pid
         }))
     } else {
+        let reason = match state.status_of(&session_id) {
+            Some(status) => format!("Session is already {:?}, not running", status),
+            None => "No Claude Code session with that id".to_string(),
+        };
         Ok(serde_json::json!({
             "cancelled": false,
-            "reason": "No active Claude Code process"
+            "reason": reason
         }))
     }
 }
 
-/// Read API key from secure store.
+/// List every session the backend is currently tracking (running or
+/// recently finished), so the UI can drive several generations at once.
+#[tauri::command]
+async fn list_sessions(state: State<'_, SessionManager>) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!(state.list()))
+}
+
+/// Return recent backend log lines, so a newly opened console pane can
+/// back-fill history instead of only showing log lines emitted after it
+/// was opened.
+#[tauri::command]
+async fn get_log_tail() -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!(console_log::tail()))
+}
+
+fn api_key_name(provider: Option<String>) -> String {
+    format!("apiKey_{}", provider.unwrap_or_else(|| "anthropic".to_string()))
+}
+
+/// Read and decrypt an API key from the secure store. Fails cleanly (rather
+/// than panicking) if the keystore is locked.
 #[tauri::command]
 async fn get_api_key(
     app: AppHandle,
+    keystore: State<'_, Keystore>,
     provider: Option<String>,
 ) -> Result<serde_json::Value, String> {
     let store = app
         .store("mindmapper-settings.json")
         .map_err(|e| format!("Failed to open store: {}", e))?;
 
-    let key_name = format!("apiKey_{}", provider.unwrap_or_else(|| "anthropic".to_string()));
-    let value = store.get(&key_name);
+    let key_name = api_key_name(provider);
+    let Some(value) = store.get(&key_name) else {
+        return Ok(serde_json::json!({ "key": null, "provider": key_name }));
+    };
+
+    let sealed: SealedKey = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    let key = keystore.open(&sealed)?;
 
     Ok(serde_json::json!({
-        "key": value,
+        "key": key,
         "provider": key_name,
     }))
 }
 
-/// Save API key to secure store.
+/// Encrypt and save an API key to the secure store. Requires the keystore
+/// to be unlocked, since encryption needs the passphrase-derived key.
 #[tauri::command]
 async fn set_api_key(
     app: AppHandle,
+    keystore: State<'_, Keystore>,
     provider: Option<String>,
     key: String,
 ) -> Result<serde_json::Value, String> {
@@ -271,8 +208,9 @@ async fn set_api_key(
         .store("mindmapper-settings.json")
         .map_err(|e| format!("Failed to open store: {}", e))?;
 
-    let key_name = format!("apiKey_{}", provider.unwrap_or_else(|| "anthropic".to_string()));
-    store.set(&key_name, serde_json::json!(key));
+    let key_name = api_key_name(provider);
+    let sealed = keystore.seal(&key)?;
+    store.set(&key_name, serde_json::to_value(&sealed).map_err(|e| e.to_string())?);
 
     Ok(serde_json::json!({
         "saved": true,
@@ -280,29 +218,94 @@ async fn set_api_key(
     }))
 }
 
+/// Whether a provider has a key configured, without requiring the keystore
+/// to be unlocked — lets the UI show "configured but locked" state.
+#[tauri::command]
+async fn has_key(app: AppHandle, provider: Option<String>) -> Result<serde_json::Value, String> {
+    let store = app
+        .store("mindmapper-settings.json")
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    let key_name = api_key_name(provider);
+    Ok(serde_json::json!({ "hasKey": store.get(&key_name).is_some() }))
+}
+
+/// Derive the passphrase key and hold it in memory so `get_api_key`/
+/// `set_api_key` can decrypt/encrypt. Reuses the salt from any
+/// already-sealed key (so re-unlocking with the same passphrase recovers
+/// the same derived key); generates a fresh salt the first time. If a key
+/// is already sealed, the passphrase is verified against it before
+/// reporting success, so a wrong passphrase is rejected here instead of
+/// surfacing later as a cryptic "decryption failed" from `get_api_key`.
+#[tauri::command]
+async fn unlock_keystore(
+    app: AppHandle,
+    keystore: State<'_, Keystore>,
+    passphrase: String,
+) -> Result<serde_json::Value, String> {
+    let store = app
+        .store("mindmapper-settings.json")
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    let existing_sealed = store
+        .entries()
+        .into_iter()
+        .find(|(key, _)| key.starts_with("apiKey_"))
+        .and_then(|(_, value)| serde_json::from_value::<SealedKey>(value).ok());
+
+    let salt = existing_sealed
+        .as_ref()
+        .map(Keystore::salt_of)
+        .transpose()?
+        .unwrap_or_else(Keystore::random_salt);
+
+    keystore.unlock(&passphrase, salt)?;
+
+    if let Some(sealed) = existing_sealed {
+        if keystore.open(&sealed).is_err() {
+            keystore.lock();
+            return Err("Incorrect passphrase".to_string());
+        }
+    }
+
+    Ok(serde_json::json!({ "unlocked": true }))
+}
+
+/// Clear the passphrase-derived key from memory.
+#[tauri::command]
+async fn lock_keystore(keystore: State<'_, Keystore>) -> Result<serde_json::Value, String> {
+    keystore.lock();
+    Ok(serde_json::json!({ "unlocked": false }))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .manage(ClaudeProcess(Mutex::new(None)))
+        .manage(SessionManager::new())
+        .manage(Keystore::new())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-            }
+            // Bridges `log::*!` calls at Info level and above to a
+            // `backend:log` event so the
+            // desktop UI always has a diagnostics console, not just in
+            // debug builds.
+            console_log::ConsoleLogger::init(app.handle().clone());
+            ipc::start(app.handle().clone());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             detect_claude_cli,
             spawn_claude,
             cancel_claude,
+            list_sessions,
+            get_log_tail,
             get_api_key,
             set_api_key,
+            has_key,
+            unlock_keystore,
+            lock_keystore,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
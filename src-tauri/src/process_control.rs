@@ -0,0 +1,57 @@
+//! Process-group signalling for Unix. `cancel_claude` used to call
+//! `libc::kill` on only the direct child, but `claude` in hands-off mode
+//! spawns Bash/tool subprocesses that keep running (and keep writing to the
+//! piped stdout) after the direct child is gone. Running the child in its
+//! own process group lets us signal the whole tree at once.
+
+/// Put the about-to-be-spawned child in its own process group, so it (and
+/// anything it spawns) can be signalled as a unit. Safety: `setpgid` is
+/// async-signal-safe and is the only thing done between fork and exec.
+#[cfg(not(target_os = "windows"))]
+pub fn new_process_group(command: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Send SIGTERM to every process in `pid`'s process group.
+///
+/// Callers must only pass a PID belonging to a session that's still
+/// `Running` (`SessionManager::pid_of` already enforces this) — because the
+/// child is its own process group leader, `-pid` here doubles as its pgid,
+/// and signalling a stale, OS-recycled PID would tear down an unrelated
+/// process group instead of a no-op.
+#[cfg(not(target_os = "windows"))]
+pub fn terminate_group(pid: u32) {
+    if pid == 0 {
+        return;
+    }
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGTERM);
+    }
+}
+
+/// Send SIGKILL to every process in `pid`'s process group. See
+/// `terminate_group` for why `pid` must come from a still-`Running` session.
+#[cfg(not(target_os = "windows"))]
+pub fn kill_group(pid: u32) {
+    if pid == 0 {
+        return;
+    }
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGKILL);
+    }
+}
+
+/// Whether the process `pid` still exists, checked via the null signal.
+#[cfg(not(target_os = "windows"))]
+pub fn process_alive(pid: u32) -> bool {
+    unsafe { libc::kill(pid as i32, 0) == 0 }
+}
@@ -0,0 +1,137 @@
+//! Registry of concurrently running Claude Code sessions.
+//!
+//! `spawn_claude` used to stash a single child PID in `ClaudeProcess`, which
+//! meant a second spawn silently clobbered the first and `cancel_claude` had
+//! no way to say *which* process to stop. `SessionManager` keeps one
+//! `SessionHandle` per session so the UI can drive several generations in
+//! parallel and cancel them independently.
+
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::stream_json::UsageTotals;
+
+/// Lifecycle state of a tracked Claude Code session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Everything we know about one running (or just-finished) `claude` child.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionHandle {
+    pub session_id: String,
+    /// The child's PID, cleared once the session reaches a terminal status
+    /// so a stale/finished session can never be signalled again — PIDs get
+    /// reused by the OS, so holding onto one past process exit is unsafe.
+    pub pid: Option<u32>,
+    pub model: Option<String>,
+    pub output_dir: String,
+    /// Unix timestamp (seconds) the session was spawned.
+    pub started_at: u64,
+    pub status: SessionStatus,
+    pub usage: UsageTotals,
+    /// Set once a cancellation has been resolved, recording whether the
+    /// process group had to be escalated to SIGKILL after not responding
+    /// to SIGTERM within the grace period.
+    pub cancel_escalated: Option<bool>,
+}
+
+impl SessionHandle {
+    fn new(session_id: String, pid: u32, model: Option<String>, output_dir: String) -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            session_id,
+            pid: Some(pid),
+            model,
+            output_dir,
+            started_at,
+            status: SessionStatus::Running,
+            usage: UsageTotals::default(),
+            cancel_escalated: None,
+        }
+    }
+}
+
+/// Thread-safe registry of active and recently-finished sessions, keyed by
+/// UUID session id rather than PID (PIDs get reused by the OS, which made
+/// `session_{pid}` ids ambiguous across the lifetime of the app).
+#[derive(Default)]
+pub struct SessionManager(Mutex<std::collections::HashMap<String, SessionHandle>>);
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a newly spawned child and return its generated session id.
+    pub fn register(&self, pid: u32, model: Option<String>, output_dir: String) -> String {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let handle = SessionHandle::new(session_id.clone(), pid, model, output_dir);
+        self.0
+            .lock()
+            .expect("session map poisoned")
+            .insert(session_id.clone(), handle);
+        session_id
+    }
+
+    /// Look up a session's PID, for signalling it. Only returns a PID for a
+    /// session that's still `Running` — once a session reaches a terminal
+    /// status its PID is cleared, so a stale or typo'd session id can never
+    /// resolve to a PID the OS may have since reused for something else.
+    pub fn pid_of(&self, session_id: &str) -> Option<u32> {
+        self.0.lock().expect("session map poisoned").get(session_id).and_then(|h| h.pid)
+    }
+
+    /// Mark a session's terminal status (once its process has exited, or as
+    /// soon as `cancel_claude` starts cancelling it), and prune its PID so it
+    /// can't be signalled again through `pid_of`.
+    pub fn finish(&self, session_id: &str, status: SessionStatus) {
+        if let Some(handle) = self.0.lock().expect("session map poisoned").get_mut(session_id) {
+            handle.status = status;
+            handle.pid = None;
+        }
+    }
+
+    /// Current lifecycle status of a session, if it's still tracked.
+    pub fn status_of(&self, session_id: &str) -> Option<SessionStatus> {
+        self.0.lock().expect("session map poisoned").get(session_id).map(|h| h.status)
+    }
+
+    /// Record whether a cancellation had to escalate to SIGKILL.
+    pub fn set_escalated(&self, session_id: &str, escalated: bool) {
+        if let Some(handle) = self.0.lock().expect("session map poisoned").get_mut(session_id) {
+            handle.cancel_escalated = Some(escalated);
+        }
+    }
+
+    /// Whether a cancelled session's kill had to escalate to SIGKILL.
+    pub fn escalated_of(&self, session_id: &str) -> Option<bool> {
+        self.0.lock().expect("session map poisoned").get(session_id).and_then(|h| h.cancel_escalated)
+    }
+
+    /// Fold a newly observed usage/cost event into a session's running
+    /// totals, returning the updated totals for the caller to emit.
+    pub fn apply_usage(&self, session_id: &str, event: &crate::stream_json::ClaudeEvent) -> Option<UsageTotals> {
+        let mut sessions = self.0.lock().expect("session map poisoned");
+        let handle = sessions.get_mut(session_id)?;
+        handle.usage.apply(event);
+        Some(handle.usage.clone())
+    }
+
+    /// Snapshot of every tracked session, for the `list_sessions` command.
+    pub fn list(&self) -> Vec<SessionHandle> {
+        self.0.lock().expect("session map poisoned").values().cloned().collect()
+    }
+}